@@ -0,0 +1,221 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::validators::ChunkedDbIter;
+use crate::{
+    blocks::BlockHeader,
+    chain_storage::{BlockchainBackend, BlockchainDatabase, Hash},
+    transactions::types::HashDigest,
+    validation::ValidationError,
+};
+use digest::Digest;
+use log::*;
+
+const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::cht";
+
+/// Number of headers grouped under a single canonical-hash-trie epoch root, matching Bitcoin's difficulty
+/// retarget window.
+pub const CHT_EPOCH_SIZE: u64 = 2016;
+
+/// An inclusion proof that a `(height, header_hash)` leaf is part of a canonical-hash-trie epoch root. Unlike
+/// walking `HeaderIter` from tip to the header in question, verifying a `MerkleProof` against a trusted epoch root
+/// is `O(log n)` in the epoch size and does not require fetching any intermediate headers.
+#[derive(Debug, Clone)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    pub fn verify(&self, leaf: &Hash, root: &Hash) -> bool {
+        let mut hash = leaf.clone();
+        let mut index = self.index;
+        for sibling in &self.siblings {
+            let mut digest = HashDigest::new();
+            if index % 2 == 0 {
+                digest = digest.chain(&hash).chain(sibling);
+            } else {
+                digest = digest.chain(sibling).chain(&hash);
+            }
+            hash = digest.result().to_vec();
+            index /= 2;
+        }
+        &hash == root
+    }
+}
+
+pub(crate) fn leaf_hash(height: u64, header_hash: &Hash) -> Hash {
+    HashDigest::new()
+        .chain(height.to_le_bytes())
+        .chain(header_hash)
+        .result()
+        .to_vec()
+}
+
+fn merkle_root(leaves: &[Hash]) -> Hash {
+    let mut level = leaves.to_vec();
+    if level.is_empty() {
+        return HashDigest::new().result().to_vec();
+    }
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level.last().unwrap().clone());
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| HashDigest::new().chain(&pair[0]).chain(&pair[1]).result().to_vec())
+            .collect();
+    }
+    level.remove(0)
+}
+
+fn merkle_proof(leaves: &[Hash], index: usize) -> MerkleProof {
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut idx = index;
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level.last().unwrap().clone());
+        }
+        let sibling_idx = if idx % 2 == 0 { idx + 1 } else { idx - 1 };
+        siblings.push(level[sibling_idx].clone());
+        level = level
+            .chunks(2)
+            .map(|pair| HashDigest::new().chain(&pair[0]).chain(&pair[1]).result().to_vec())
+            .collect();
+        idx /= 2;
+    }
+    MerkleProof { index, siblings }
+}
+
+/// Builds and queries the canonical-hash-trie: a sequence of Merkle roots, one per fixed-size epoch of headers,
+/// over that epoch's `(height -> header_hash)` leaves. A node that already trusts an epoch's root (anchored, for
+/// example, in a consensus checkpoint) can verify any single header in that epoch in `O(log epoch_size)` instead of
+/// fetching and re-linking the entire header chain up to it.
+pub struct CanonicalHashTrie<'a, B> {
+    db: &'a BlockchainDatabase<B>,
+}
+
+impl<'a, B: BlockchainBackend> CanonicalHashTrie<'a, B> {
+    pub fn new(db: &'a BlockchainDatabase<B>) -> Self {
+        Self { db }
+    }
+
+    fn epoch_bounds(height: u64) -> (u64, u64) {
+        let epoch = height / CHT_EPOCH_SIZE;
+        let start = epoch * CHT_EPOCH_SIZE;
+        (start, start + CHT_EPOCH_SIZE)
+    }
+
+    fn epoch_leaves(&self, epoch_start: u64, epoch_end: u64) -> Result<Vec<Hash>, ValidationError> {
+        let mut leaves = Vec::with_capacity(CHT_EPOCH_SIZE as usize);
+        let header_iter = ChunkedDbIter::new(100, move |cursor: u64, chunk_size: usize| {
+            let start = epoch_start + cursor;
+            let end = std::cmp::min(start + chunk_size as u64, epoch_end);
+            if start >= end {
+                return Ok(Vec::new());
+            }
+            self.db.fetch_headers((start..end).collect())
+        });
+        for (i, header) in header_iter.enumerate() {
+            let header = header.map_err(ValidationError::custom_error)?;
+            leaves.push(leaf_hash(epoch_start + i as u64, &header.hash()));
+        }
+        Ok(leaves)
+    }
+
+    /// Computes the canonical-hash-trie root for the epoch containing `height`.
+    pub fn epoch_root(&self, height: u64) -> Result<Hash, ValidationError> {
+        let (start, end) = Self::epoch_bounds(height);
+        let leaves = self.epoch_leaves(start, end)?;
+        trace!(
+            target: LOG_TARGET,
+            "Computed CHT root over {} leaves for epoch starting at height {}",
+            leaves.len(),
+            start
+        );
+        Ok(merkle_root(&leaves))
+    }
+
+    /// Fetches `height`'s header along with a Merkle proof of its inclusion in its epoch's CHT root, and that root
+    /// itself. The caller is responsible for checking the returned root against one it already trusts (see
+    /// `HorizonHeadersValidator`'s `StatelessValidation<HeaderProof>` impl, which checks it against the nearest
+    /// trusted checkpoint) before relying on `MerkleProof::verify` against it.
+    pub fn fetch_header_proof(&self, height: u64) -> Result<(BlockHeader, MerkleProof, Hash), ValidationError> {
+        let (start, end) = Self::epoch_bounds(height);
+        let leaves = self.epoch_leaves(start, end)?;
+        let index = (height - start) as usize;
+        let proof = merkle_proof(&leaves, index);
+        let root = merkle_root(&leaves);
+        let header = self.db.fetch_header(height).map_err(ValidationError::custom_error)?;
+        Ok((header, proof, root))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaves(n: u64) -> Vec<Hash> {
+        (0..n).map(|i| leaf_hash(i, &vec![i as u8; 32])).collect()
+    }
+
+    #[test]
+    fn every_leaf_proves_inclusion_in_the_root() {
+        for n in &[1u64, 2, 3, 5, 8, 13] {
+            let leaves = leaves(*n);
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index);
+                assert!(
+                    proof.verify(leaf, &root),
+                    "leaf {} did not verify against the root for {} leaves",
+                    index,
+                    n
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_proof_does_not_verify_against_the_wrong_leaf() {
+        let leaves = leaves(5);
+        let root = merkle_root(&leaves);
+        let proof = merkle_proof(&leaves, 2);
+        assert!(!proof.verify(&leaves[3], &root));
+    }
+
+    #[test]
+    fn a_proof_does_not_verify_against_the_wrong_root() {
+        let leaves_a = leaves(5);
+        let leaves_b = leaves(7);
+        let root_b = merkle_root(&leaves_b);
+        let proof = merkle_proof(&leaves_a, 0);
+        assert!(!proof.verify(&leaves_a[0], &root_b));
+    }
+
+    #[test]
+    fn leaf_hash_is_sensitive_to_height() {
+        let header_hash = vec![7u8; 32];
+        assert_ne!(leaf_hash(1, &header_hash), leaf_hash(2, &header_hash));
+    }
+}
@@ -21,14 +21,23 @@
 //  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 mod headers;
-pub use headers::HorizonHeadersValidator;
+pub use headers::{HeaderProof, HorizonHeadersValidator};
 
 mod chain_balance;
-pub use chain_balance::{ChainBalanceValidator, HeaderIter};
+pub use chain_balance::{ChainBalanceValidator, ChunkedDbIter, HeaderIter};
 
 mod mmr_roots;
 pub use mmr_roots::MmrRootsValidator;
 
+mod checkpoint;
+pub use checkpoint::{Checkpoint, CheckpointValidator};
+
+mod snapshot;
+pub use snapshot::{MmrRangeProof, SnapshotChunk, SnapshotFormat, SnapshotValidator};
+
+mod backfill;
+pub use backfill::AncientBlockValidator;
+
 use crate::{
     blocks::BlockHeader,
     chain_storage::{BlockchainBackend, BlockchainDatabase},
@@ -42,17 +51,20 @@ use std::{fmt, sync::Arc};
 pub struct HorizonSyncValidators {
     pub header: Arc<StatelessValidator<BlockHeader>>,
     pub final_state: Arc<StatelessValidator<u64>>,
+    pub snapshot: Arc<StatelessValidator<SnapshotChunk>>,
 }
 
 impl HorizonSyncValidators {
-    pub fn new<THeader, TFinal>(header: THeader, final_state: TFinal) -> Self
+    pub fn new<THeader, TFinal, TSnapshot>(header: THeader, final_state: TFinal, snapshot: TSnapshot) -> Self
     where
         THeader: StatelessValidation<BlockHeader> + 'static,
         TFinal: StatelessValidation<u64> + 'static,
+        TSnapshot: StatelessValidation<SnapshotChunk> + 'static,
     {
         Self {
             header: Arc::new(Box::new(header)),
             final_state: Arc::new(Box::new(final_state)),
+            snapshot: Arc::new(Box::new(snapshot)),
         }
     }
 
@@ -64,7 +76,11 @@ impl HorizonSyncValidators {
     {
         Self::new(
             HorizonHeadersValidator::new(db.clone(), rules.clone()),
-            ChainBalanceValidator::new(db.clone(), rules.clone(), factories).chain(MmrRootsValidator::new(db, rules)),
+            CheckpointValidator::new(db.clone(), rules.clone()).chain(
+                ChainBalanceValidator::new(db.clone(), rules.clone(), factories)
+                    .chain(MmrRootsValidator::new(db.clone(), rules)),
+            ),
+            SnapshotValidator::new(db),
         )
     }
 }
@@ -74,6 +90,7 @@ impl fmt::Debug for HorizonSyncValidators {
         f.debug_struct("HorizonHeaderValidators")
             .field("header", &"...")
             .field("final_state", &"...")
+            .field("snapshot", &"...")
             .finish()
     }
 }
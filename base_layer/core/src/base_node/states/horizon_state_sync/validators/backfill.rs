@@ -0,0 +1,214 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::chain_balance::aggregate_genesis_commitment;
+use crate::{
+    blocks::{Block, BlockHeader},
+    chain_storage::{BlockchainBackend, BlockchainDatabase, Hash, MmrTree},
+    consensus::ConsensusManager,
+    validation::{StatelessValidation, ValidationError},
+};
+use log::*;
+
+const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::backfill";
+
+/// Validates blocks being re-imported below the pruning horizon after horizon sync, against the MMR roots the node
+/// already trusts from the header it synced to. Unlike normal block validation, an ancient block is never the tip:
+/// it is checked against the *next* block's MMR roots (`h + 1`), since that is the last point the chain's state is
+/// still anchored to something the node has already verified.
+///
+/// Backfill is interruptible and idempotent: [AncientBlockValidator::lowest_imported_height] exposes the lowest
+/// height that has been fully imported and validated so far, so an archival node can resume a backfill run (or
+/// retry after a crash) from where it left off rather than redoing already-validated history.
+pub struct AncientBlockValidator<B> {
+    db: BlockchainDatabase<B>,
+    rules: ConsensusManager,
+}
+
+impl<B: BlockchainBackend> AncientBlockValidator<B> {
+    pub fn new(db: BlockchainDatabase<B>, rules: ConsensusManager) -> Self {
+        Self { db, rules }
+    }
+
+    /// The lowest height that has been fully imported and validated so far. `Some(0)` once backfill has reached the
+    /// genesis block.
+    pub fn lowest_imported_height(&self) -> Result<Option<u64>, ValidationError> {
+        self.db
+            .fetch_lowest_imported_height()
+            .map_err(ValidationError::custom_error)
+    }
+
+    /// Checks `block`'s outputs against `next_header.output_mr`, which commits to the *entire* UTXO MMR as it stood
+    /// at `h + 1`, not just the handful of leaves this one block added. So rather than recomputing a root from this
+    /// block's outputs alone (which can never reproduce a whole-tree root), this fetches the full node history up to
+    /// `h + 1` - the same way `MmrRootsValidator` does for the tip - recomputes the whole root from it, and
+    /// additionally checks that this block's own output hashes occupy exactly the position range the MMR's node
+    /// count says they should, so a peer can't satisfy the root check with someone else's leaves sitting at this
+    /// block's position.
+    fn check_output_mr(&self, block: &Block, next_header: &BlockHeader) -> Result<(), ValidationError> {
+        let height = block.header.height;
+        let node_count = self
+            .db
+            .fetch_mmr_node_count(MmrTree::Utxo, height + 1)
+            .map_err(ValidationError::custom_error)?;
+        let nodes = self
+            .db
+            .fetch_mmr_nodes(MmrTree::Utxo, 0, node_count, None)
+            .map_err(ValidationError::custom_error)?;
+
+        let mut additions = Vec::new();
+        let mut deletions = Vec::new();
+        for (hash, is_stxo) in &nodes {
+            if *is_stxo {
+                deletions.push(hash.clone());
+            } else {
+                additions.push(hash.clone());
+            }
+        }
+        let output_mr = self
+            .db
+            .calculate_mmr_root(MmrTree::Utxo, additions, deletions)
+            .map_err(ValidationError::custom_error)?;
+        if output_mr != next_header.output_mr {
+            return Err(ValidationError::InvalidOutputMr);
+        }
+
+        let start = if height == 0 {
+            0
+        } else {
+            self.db
+                .fetch_mmr_node_count(MmrTree::Utxo, height - 1)
+                .map_err(ValidationError::custom_error)? as usize
+        };
+        let end = self
+            .db
+            .fetch_mmr_node_count(MmrTree::Utxo, height)
+            .map_err(ValidationError::custom_error)? as usize;
+        let own_leaves = block.body.outputs().into_iter().map(|o| o.hash()).collect::<Vec<_>>();
+        let leaves_at_position = nodes
+            .get(start..end)
+            .unwrap_or(&[])
+            .iter()
+            .map(|(hash, _)| hash.clone())
+            .collect::<Vec<Hash>>();
+        if leaves_at_position != own_leaves {
+            return Err(ValidationError::custom_error(format!(
+                "Ancient block at height {} does not occupy the expected UTXO MMR position range [{}, {})",
+                height, start, end
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Mirrors [Self::check_output_mr] for the kernel MMR: kernels have no deletions, so there is no bitmap to
+    /// reconcile, but the same whole-tree recompute plus position check applies.
+    fn check_kernel_mr(&self, block: &Block, next_header: &BlockHeader) -> Result<(), ValidationError> {
+        let height = block.header.height;
+        let node_count = self
+            .db
+            .fetch_mmr_node_count(MmrTree::Kernel, height + 1)
+            .map_err(ValidationError::custom_error)?;
+        let hashes = self
+            .db
+            .fetch_mmr_nodes(MmrTree::Kernel, 0, node_count, None)
+            .map_err(ValidationError::custom_error)?
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect::<Vec<Hash>>();
+        let kernel_mr = self
+            .db
+            .calculate_mmr_root(MmrTree::Kernel, hashes.clone(), vec![])
+            .map_err(ValidationError::custom_error)?;
+        if kernel_mr != next_header.kernel_mr {
+            return Err(ValidationError::InvalidKernelMr);
+        }
+
+        let start = if height == 0 {
+            0
+        } else {
+            self.db
+                .fetch_mmr_node_count(MmrTree::Kernel, height - 1)
+                .map_err(ValidationError::custom_error)? as usize
+        };
+        let end = self
+            .db
+            .fetch_mmr_node_count(MmrTree::Kernel, height)
+            .map_err(ValidationError::custom_error)? as usize;
+        let own_leaves = block.body.kernels().into_iter().map(|k| k.hash()).collect::<Vec<_>>();
+        let leaves_at_position = hashes.get(start..end).unwrap_or(&[]).to_vec();
+        if leaves_at_position != own_leaves {
+            return Err(ValidationError::custom_error(format!(
+                "Ancient block at height {} does not occupy the expected kernel MMR position range [{}, {})",
+                height, start, end
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_genesis_transition(&self, block: &Block) -> Result<(), ValidationError> {
+        if block.header.height != 0 {
+            return Ok(());
+        }
+        let expected = aggregate_genesis_commitment(&self.rules);
+        let actual = block
+            .body
+            .outputs()
+            .into_iter()
+            .filter(|u| !u.is_coinbase())
+            .map(|u| &u.commitment)
+            .sum();
+        if expected != actual {
+            return Err(ValidationError::custom_error(
+                "Ancient genesis block did not restore the expected genesis commitment transition",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<Block> for AncientBlockValidator<B> {
+    fn validate(&self, block: &Block) -> Result<(), ValidationError> {
+        let height = block.header.height;
+        debug!(target: LOG_TARGET, "Validating ancient block at height {}", height);
+
+        let next_header = self
+            .db
+            .fetch_header(height + 1)
+            .map_err(ValidationError::custom_error)?;
+
+        self.check_output_mr(block, &next_header)?;
+        self.check_kernel_mr(block, &next_header)?;
+
+        self.check_genesis_transition(block)?;
+
+        let is_lowest = self.lowest_imported_height()?.map_or(true, |lowest| height < lowest);
+        if is_lowest {
+            self.db
+                .set_lowest_imported_height(height)
+                .map_err(ValidationError::custom_error)?;
+        }
+
+        Ok(())
+    }
+}
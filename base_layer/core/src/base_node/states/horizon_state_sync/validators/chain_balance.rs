@@ -72,6 +72,22 @@ impl<B: BlockchainBackend> StatelessValidation<u64> for ChainBalanceValidator<B>
     }
 }
 
+const CHUNK_SIZE: usize = 1000;
+
+/// The sum of unspent genesis block UTXOs (excl. coinbase). Shared with [AncientBlockValidator](
+/// super::AncientBlockValidator), which needs the same genesis commitment to restore the transition into the
+/// earliest block it backfills.
+pub(crate) fn aggregate_genesis_commitment(rules: &ConsensusManager) -> Commitment {
+    rules
+        .get_genesis_block()
+        .body
+        .outputs()
+        .into_iter()
+        .filter(|u| !u.is_coinbase())
+        .map(|u| &u.commitment)
+        .sum()
+}
+
 impl<B: BlockchainBackend> ChainBalanceValidator<B> {
     fn fetch_total_offset_commitment(&self, height: u64) -> Result<Commitment, ValidationError> {
         let header_iter = HeaderIter::new(&self.db, height, 50);
@@ -88,9 +104,19 @@ impl<B: BlockchainBackend> ChainBalanceValidator<B> {
     }
 
     fn fetch_aggregate_utxo_commitment(&self) -> Result<Commitment, ValidationError> {
-        let utxos = self.db.fetch_all_utxos().map_err(ValidationError::custom_error)?;
-        trace!(target: LOG_TARGET, "Fetched {} UTXOs", utxos.len());
-        Ok(utxos.into_iter().map(|u| u.commitment).sum())
+        let db = &self.db;
+        let utxo_iter = ChunkedDbIter::new(CHUNK_SIZE, move |cursor, chunk_size| {
+            db.fetch_utxos_in_range(cursor, cursor + chunk_size as u64)
+        });
+        let mut total = self.commit_value(0u64.into());
+        let mut count = 0u64;
+        for utxo in utxo_iter {
+            let utxo = utxo.map_err(ValidationError::custom_error)?;
+            count += 1;
+            total = &total + &utxo.commitment;
+        }
+        trace!(target: LOG_TARGET, "Fetched {} UTXOs", count);
+        Ok(total)
     }
 
     fn get_emission_commitment_at(&self, height: u64) -> Commitment {
@@ -106,21 +132,23 @@ impl<B: BlockchainBackend> ChainBalanceValidator<B> {
     }
 
     fn get_aggregate_genesis_commitment(&self) -> Commitment {
-        // Get the sum of unspent genesis block UTXOs (excl coinbase)
-        self.rules
-            .get_genesis_block()
-            .body
-            .outputs()
-            .into_iter()
-            .filter(|u| !u.is_coinbase())
-            .map(|u| &u.commitment)
-            .sum()
+        aggregate_genesis_commitment(&self.rules)
     }
 
     fn fetch_aggregate_kernel_excess(&self) -> Result<Commitment, ValidationError> {
-        let kernels = self.db.fetch_all_kernels().map_err(ValidationError::custom_error)?;
-        trace!(target: LOG_TARGET, "Fetched {} kernels", kernels.len());
-        Ok(kernels.into_iter().map(|k| k.excess).sum())
+        let db = &self.db;
+        let kernel_iter = ChunkedDbIter::new(CHUNK_SIZE, move |cursor, chunk_size| {
+            db.fetch_kernels_in_range(cursor, cursor + chunk_size as u64)
+        });
+        let mut total = self.commit_value(0u64.into());
+        let mut count = 0u64;
+        for kernel in kernel_iter {
+            let kernel = kernel.map_err(ValidationError::custom_error)?;
+            count += 1;
+            total = &total + &kernel.excess;
+        }
+        trace!(target: LOG_TARGET, "Fetched {} kernels", count);
+        Ok(total)
     }
 
     #[inline]
@@ -129,64 +157,129 @@ impl<B: BlockchainBackend> ChainBalanceValidator<B> {
     }
 }
 
-// TODO: This is probably generally useful and can be generalized for any DB "item" that we want to load in chunks
-/// Iterator that emits BlockHeaders until a given height. This iterator loads headers in chunks of size `chunk_size`
-/// for a low memory footprint. The chunk buffer is allocated once and reused.
-pub struct HeaderIter<'a, B> {
-    chunk: Vec<BlockHeader>,
+/// Iterator that pages through a DB collection in fixed-size windows, for a low memory footprint, reusing a single
+/// chunk buffer rather than materializing the whole collection upfront. `fetch_chunk(cursor, chunk_size)` is called
+/// to load the next window and should return fewer than `chunk_size` items (or none) once the end of the collection
+/// has been reached. This generalizes what `HeaderIter` used to do just for `BlockHeader`s to any DB item.
+pub struct ChunkedDbIter<T, F> {
+    chunk: Vec<T>,
     chunk_size: usize,
-    cursor: usize,
-    is_error: bool,
-    height: u64,
-    db: &'a BlockchainDatabase<B>,
+    cursor: u64,
+    is_done: bool,
+    fetch_chunk: F,
 }
 
-impl<'a, B> HeaderIter<'a, B> {
-    pub fn new(db: &'a BlockchainDatabase<B>, height: u64, chunk_size: usize) -> Self {
+impl<T, F, E> ChunkedDbIter<T, F>
+where F: FnMut(u64, usize) -> Result<Vec<T>, E>
+{
+    pub fn new(chunk_size: usize, fetch_chunk: F) -> Self {
         Self {
-            db,
+            chunk: Vec::with_capacity(chunk_size),
             chunk_size,
             cursor: 0,
-            is_error: false,
-            height,
-            chunk: Vec::with_capacity(chunk_size),
+            is_done: false,
+            fetch_chunk,
         }
     }
-
-    fn next_chunk(&self) -> Vec<u64> {
-        let upper_bound = cmp::min(self.cursor + self.chunk_size, self.height as usize);
-        (self.cursor..=upper_bound).map(|n| n as u64).collect()
-    }
 }
 
-impl<B: BlockchainBackend> Iterator for HeaderIter<'_, B> {
-    type Item = Result<BlockHeader, ChainStorageError>;
+impl<T, F, E> Iterator for ChunkedDbIter<T, F>
+where F: FnMut(u64, usize) -> Result<Vec<T>, E>
+{
+    type Item = Result<T, E>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.is_error {
-            return None;
-        }
-
         if self.chunk.is_empty() {
-            let block_nums = self.next_chunk();
-            // We're done: No more block headers to fetch
-            if block_nums.is_empty() {
+            if self.is_done {
                 return None;
             }
 
-            match self.db.fetch_headers(block_nums) {
-                Ok(headers) => {
-                    self.cursor += headers.len();
-                    self.chunk.extend(headers);
+            match (self.fetch_chunk)(self.cursor, self.chunk_size) {
+                Ok(items) => {
+                    // We're done: No more items to fetch
+                    if items.is_empty() {
+                        self.is_done = true;
+                        return None;
+                    }
+                    self.cursor += items.len() as u64;
+                    if items.len() < self.chunk_size {
+                        self.is_done = true;
+                    }
+                    // Stored reversed so items can be popped off the end in order, keeping the same buffer
+                    self.chunk.extend(items.into_iter().rev());
                 },
                 Err(err) => {
                     // On the next call, the iterator will end
-                    self.is_error = true;
+                    self.is_done = true;
                     return Some(Err(err));
                 },
             }
         }
 
-        Some(Ok(self.chunk.remove(0)))
+        self.chunk.pop().map(Ok)
+    }
+}
+
+/// Iterator that emits `BlockHeader`s up to and including `height`, in chunks of `chunk_size + 1` headers, for a
+/// low memory footprint.
+pub struct HeaderIter<'a, B> {
+    inner: ChunkedDbIter<BlockHeader, Box<dyn FnMut(u64, usize) -> Result<Vec<BlockHeader>, ChainStorageError> + 'a>>,
+}
+
+impl<'a, B: BlockchainBackend> HeaderIter<'a, B> {
+    pub fn new(db: &'a BlockchainDatabase<B>, height: u64, chunk_size: usize) -> Self {
+        let fetch_chunk = move |cursor: u64, chunk_size: usize| -> Result<Vec<BlockHeader>, ChainStorageError> {
+            let upper_bound = cmp::min(cursor + chunk_size as u64, height);
+            if cursor > upper_bound {
+                return Ok(Vec::new());
+            }
+            db.fetch_headers((cursor..=upper_bound).collect())
+        };
+        Self {
+            inner: ChunkedDbIter::new(chunk_size, Box::new(fetch_chunk)),
+        }
+    }
+}
+
+impl<B> Iterator for HeaderIter<'_, B> {
+    type Item = Result<BlockHeader, ChainStorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_pages_through_full_chunks_and_a_short_final_chunk() {
+        let items: Vec<u32> = (0..25).collect();
+        let iter = ChunkedDbIter::new(10, |cursor: u64, chunk_size: usize| {
+            let start = cursor as usize;
+            let end = cmp::min(start + chunk_size, items.len());
+            Ok::<_, ()>(items.get(start..end).unwrap_or(&[]).to_vec())
+        });
+        let collected: Vec<u32> = iter.map(|item| item.unwrap()).collect();
+        assert_eq!(collected, items);
+    }
+
+    #[test]
+    fn it_stops_on_an_empty_chunk() {
+        let iter = ChunkedDbIter::new(5, |_cursor: u64, _chunk_size: usize| Ok::<Vec<u32>, ()>(Vec::new()));
+        let collected: Vec<u32> = iter.map(|item| item.unwrap()).collect();
+        assert!(collected.is_empty());
+    }
+
+    #[test]
+    fn it_surfaces_a_fetch_error_and_then_stops() {
+        let mut calls = 0u32;
+        let mut iter = ChunkedDbIter::new(5, move |_cursor: u64, _chunk_size: usize| {
+            calls += 1;
+            Err::<Vec<u32>, _>("fetch failed")
+        });
+        assert_eq!(iter.next(), Some(Err("fetch failed")));
+        assert_eq!(iter.next(), None);
     }
 }
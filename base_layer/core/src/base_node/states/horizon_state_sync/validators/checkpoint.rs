@@ -0,0 +1,119 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::{
+    blocks::BlockHeader,
+    chain_storage::{BlockchainBackend, BlockchainDatabase},
+    consensus::ConsensusManager,
+    validation::{StatelessValidation, ValidationError},
+};
+use log::*;
+
+const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::checkpoint";
+
+// `Checkpoint` itself now lives in the consensus layer (see `consensus::checkpoint`), so that
+// `ConsensusManager::consensus_constants()::nearest_checkpoint()` can hand one back without the `consensus` module
+// depending back on `base_node`. Re-exported here so existing `checkpoint::Checkpoint` callers, including
+// `validators.rs`'s `pub use`, keep resolving.
+pub use crate::consensus::checkpoint::Checkpoint;
+
+/// Rejects a horizon header whose ancestry does not reconcile with the nearest trusted checkpoint at or before its
+/// height, guarding against a peer offering a lower-work but internally-consistent fake horizon state. This is
+/// intended to run ahead of the more expensive `ChainBalanceValidator`/`MmrRootsValidator` checks, so that a bad
+/// horizon header is rejected before any MMR or balance validation work is done.
+pub struct CheckpointValidator<B> {
+    db: BlockchainDatabase<B>,
+    rules: ConsensusManager,
+}
+
+impl<B: BlockchainBackend> CheckpointValidator<B> {
+    pub fn new(db: BlockchainDatabase<B>, rules: ConsensusManager) -> Self {
+        Self { db, rules }
+    }
+
+    fn check_header(&self, header: &BlockHeader) -> Result<(), ValidationError> {
+        let checkpoint = self
+            .rules
+            .consensus_constants()
+            .nearest_checkpoint(header.height)
+            .ok_or_else(|| {
+                ValidationError::custom_error(format!(
+                    "No trust checkpoint is configured at or before height {}",
+                    header.height
+                ))
+            })?;
+
+        if header.height < checkpoint.height {
+            return Err(ValidationError::custom_error(format!(
+                "Horizon header at height {} is below the nearest trusted checkpoint at height {}",
+                header.height, checkpoint.height
+            )));
+        }
+
+        let accumulated_difficulty = self
+            .db
+            .fetch_accumulated_difficulty(header.height)
+            .map_err(ValidationError::custom_error)?;
+        if accumulated_difficulty < checkpoint.accumulated_difficulty {
+            return Err(ValidationError::custom_error(format!(
+                "Horizon header at height {} carries less accumulated difficulty ({}) than the trusted checkpoint \
+                 at height {} ({})",
+                header.height, accumulated_difficulty, checkpoint.height, checkpoint.accumulated_difficulty
+            )));
+        }
+
+        if header.height == checkpoint.height {
+            if header.hash() != checkpoint.header_hash {
+                return Err(ValidationError::custom_error(
+                    "Horizon header hash did not match the trusted checkpoint",
+                ));
+            }
+            if header.kernel_mr != checkpoint.kernel_mr || header.output_mr != checkpoint.output_mr {
+                return Err(ValidationError::custom_error(
+                    "Horizon header MMR roots did not match the trusted checkpoint",
+                ));
+            }
+        }
+
+        debug!(
+            target: LOG_TARGET,
+            "Horizon header at height {} reconciles with the checkpoint at height {}", header.height, checkpoint.height
+        );
+        Ok(())
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<BlockHeader> for CheckpointValidator<B> {
+    fn validate(&self, header: &BlockHeader) -> Result<(), ValidationError> {
+        self.check_header(header)
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<u64> for CheckpointValidator<B> {
+    fn validate(&self, horizon_height: &u64) -> Result<(), ValidationError> {
+        let header = self
+            .db
+            .fetch_header(*horizon_height)
+            .map_err(ValidationError::custom_error)?;
+        self.check_header(&header)
+    }
+}
@@ -0,0 +1,105 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::{
+    base_node::states::horizon_state_sync::cht::{self, MerkleProof},
+    blocks::BlockHeader,
+    chain_storage::{BlockchainBackend, BlockchainDatabase, Hash},
+    consensus::ConsensusManager,
+    validation::{StatelessValidation, ValidationError},
+};
+use log::*;
+
+const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::headers";
+
+/// A header proof to be checked against a CHT root the node already trusts, rather than against the contiguous
+/// header chain.
+#[derive(Debug, Clone)]
+pub struct HeaderProof {
+    pub header: BlockHeader,
+    pub proof: MerkleProof,
+    pub cht_root: Hash,
+}
+
+pub struct HorizonHeadersValidator<B> {
+    rules: ConsensusManager,
+    db: BlockchainDatabase<B>,
+}
+
+impl<B: BlockchainBackend> HorizonHeadersValidator<B> {
+    pub fn new(db: BlockchainDatabase<B>, rules: ConsensusManager) -> Self {
+        Self { db, rules }
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<BlockHeader> for HorizonHeadersValidator<B> {
+    fn validate(&self, header: &BlockHeader) -> Result<(), ValidationError> {
+        debug!(
+            target: LOG_TARGET,
+            "Validating horizon header at height {}", header.height
+        );
+        self.rules
+            .consensus_constants()
+            .check_proof_of_work(header)
+            .map_err(ValidationError::custom_error)?;
+        Ok(())
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<HeaderProof> for HorizonHeadersValidator<B> {
+    /// Verifies a single historical header against a CHT root the node already trusts, in `O(log epoch_size)`,
+    /// without fetching or re-linking every intermediate header between it and the tip. This lets wallets and
+    /// pruned nodes validate spends against specific heights cheaply.
+    ///
+    /// `item.cht_root` is only ever the root the *proof* was built against - it is not trusted on its own. Before
+    /// verifying the proof, it must match the CHT root anchored in the nearest trusted checkpoint for this height;
+    /// otherwise a peer could supply a header, a self-consistent proof, and a self-computed root that all round-trip
+    /// without the root ever having been committed to by anything the node trusts.
+    fn validate(&self, item: &HeaderProof) -> Result<(), ValidationError> {
+        let checkpoint = self
+            .rules
+            .consensus_constants()
+            .nearest_checkpoint(item.header.height)
+            .ok_or_else(|| {
+                ValidationError::custom_error(format!(
+                    "No trusted checkpoint is configured at or before height {}",
+                    item.header.height
+                ))
+            })?;
+        if checkpoint.cht_root != item.cht_root {
+            return Err(ValidationError::custom_error(format!(
+                "Header proof for height {} claimed a CHT root that does not match the trusted checkpoint at \
+                 height {}",
+                item.header.height, checkpoint.height
+            )));
+        }
+
+        let leaf_hash = cht::leaf_hash(item.header.height, &item.header.hash());
+        if !item.proof.verify(&leaf_hash, &item.cht_root) {
+            return Err(ValidationError::custom_error(format!(
+                "Header proof for height {} did not verify against the trusted CHT root",
+                item.header.height
+            )));
+        }
+        Ok(())
+    }
+}
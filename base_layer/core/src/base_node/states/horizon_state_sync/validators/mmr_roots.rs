@@ -22,10 +22,13 @@
 
 use crate::{
     blocks::BlockHeader,
-    chain_storage::{BlockchainBackend, BlockchainDatabase, MmrTree},
+    chain_storage::{BlockchainBackend, BlockchainDatabase, Hash, MmrTree},
     consensus::ConsensusManager,
+    transactions::types::HashDigest,
     validation::{StatelessValidation, ValidationError},
 };
+use croaring::Bitmap;
+use digest::Digest;
 use log::*;
 
 const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::mmr_roots";
@@ -46,14 +49,20 @@ impl<B: BlockchainBackend> MmrRootsValidator<B> {
             .fetch_mmr_node_count(MmrTree::Utxo, tip_header.height)
             .map_err(ValidationError::custom_error)?;
 
-        let (additions, deletions) = self
+        let nodes = self
             .db
             .fetch_mmr_nodes(MmrTree::Utxo, 0, node_count, None)
-            .map_err(ValidationError::custom_error)?
-            .into_iter()
-            .partition::<Vec<_>, _>(|(_, is_stxo)| !*is_stxo);
-        let additions = additions.into_iter().map(|(hash, _)| hash).collect();
-        let deletions = deletions.into_iter().map(|(hash, _)| hash).collect();
+            .map_err(ValidationError::custom_error)?;
+
+        let mut additions = Vec::new();
+        let mut deletions = Vec::new();
+        for (hash, is_stxo) in &nodes {
+            if *is_stxo {
+                deletions.push(hash.clone());
+            } else {
+                additions.push(hash.clone());
+            }
+        }
         let output_mr = self
             .db
             .calculate_mmr_root(MmrTree::Utxo, additions, deletions)
@@ -61,6 +70,38 @@ impl<B: BlockchainBackend> MmrRootsValidator<B> {
         if tip_header.output_mr != output_mr {
             return Err(ValidationError::InvalidOutputMr);
         }
+
+        self.check_deletion_bitmap(tip_header, &nodes)?;
+
+        Ok(())
+    }
+
+    /// Reconstructs the roaring bitmap of spent-output (STXO) leaf indices from the fetched UTXO MMR nodes and
+    /// checks that its hash matches the deleted-bitmap hash the database holds for the horizon height. This guards
+    /// against a peer whose claimed additions hash correctly but whose claimed-spent set differs from that stored
+    /// bitmap. The bitmap is not checked against a commitment in the header itself; `tip_header` is only used for
+    /// its height.
+    fn check_deletion_bitmap(&self, tip_header: &BlockHeader, nodes: &[(Hash, bool)]) -> Result<(), ValidationError> {
+        let mut deleted = Bitmap::create();
+        deleted.add_many(
+            &nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, is_stxo))| *is_stxo)
+                .map(|(pos, _)| pos as u32)
+                .collect::<Vec<_>>(),
+        );
+        deleted.run_optimize();
+
+        let actual_hash = HashDigest::new().chain(deleted.serialize()).result().to_vec();
+        let expected_hash = self
+            .db
+            .fetch_deleted_bitmap_hash_at_height(tip_header.height)
+            .map_err(ValidationError::custom_error)?;
+        if actual_hash != expected_hash {
+            return Err(ValidationError::InvalidDeletionBitmap);
+        }
+
         Ok(())
     }
 
@@ -89,19 +130,18 @@ impl<B: BlockchainBackend> MmrRootsValidator<B> {
 }
 
 impl<B: BlockchainBackend> StatelessValidation<u64> for MmrRootsValidator<B> {
-    fn validate(&self, _horizon_height: &u64) -> Result<(), ValidationError> {
-        // TODO: Check MRs
-        // let tip_header = self
-        //     .db
-        //     .fetch_header(*horizon_height)
-        //     .map_err(ValidationError::custom_error)?;
-        // debug!(
-        //     target: LOG_TARGET,
-        //     "Validating MMR roots for horizon state at height {}", tip_header.height
-        // );
-
-        // self.check_kernel_mr(&tip_header)?;
-        // self.check_utxo_mr()?;
+    fn validate(&self, horizon_height: &u64) -> Result<(), ValidationError> {
+        let tip_header = self
+            .db
+            .fetch_header(*horizon_height)
+            .map_err(ValidationError::custom_error)?;
+        debug!(
+            target: LOG_TARGET,
+            "Validating MMR roots for horizon state at height {}", tip_header.height
+        );
+
+        self.check_kernel_mr(&tip_header)?;
+        self.check_utxo_mr(&tip_header)?;
 
         Ok(())
     }
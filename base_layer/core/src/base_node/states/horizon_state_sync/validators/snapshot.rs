@@ -0,0 +1,289 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::{
+    blocks::BlockHeader,
+    chain_storage::{BlockchainBackend, BlockchainDatabase, Hash, MmrTree},
+    transactions::types::HashDigest,
+    validation::{StatelessValidation, ValidationError},
+};
+use digest::Digest;
+use log::*;
+use std::{convert::TryFrom, ops::Range, sync::Mutex};
+
+const LOG_TARGET: &str = "c::bn::states::horizon_state_sync::snapshot";
+
+/// On-the-wire format version for a horizon state snapshot chunk. A node that receives a version it does not
+/// recognise rejects the chunk rather than guessing at its layout, so that future fields can be added to
+/// `SnapshotChunk` without breaking nodes still running an older version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    V1 = 1,
+}
+
+impl SnapshotFormat {
+    pub const CURRENT: SnapshotFormat = SnapshotFormat::V1;
+
+    pub fn as_byte(self) -> u8 {
+        self as u8
+    }
+}
+
+impl TryFrom<u8> for SnapshotFormat {
+    type Error = ValidationError;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(SnapshotFormat::V1),
+            v => Err(ValidationError::custom_error(format!(
+                "Unsupported horizon snapshot format version {}",
+                v
+            ))),
+        }
+    }
+}
+
+/// A claim about the state of the peer's Merkle Mountain Range: `peaks` are the peak hashes of the MMR over every leaf
+/// validated *before* this chunk, and `root` is the bagged root *after* folding this chunk's leaves in on top of
+/// them. Neither field is trusted at face value: [SnapshotValidator] independently maintains its own accumulator
+/// and only accepts a chunk whose `peaks` matches what it has already verified, then recomputes `root` itself from
+/// `peaks` and the chunk's leaves rather than trusting the peer's claim.
+#[derive(Debug, Clone)]
+pub struct MmrRangeProof {
+    pub peaks: Vec<Hash>,
+    pub root: Hash,
+}
+
+/// One fixed-size slice of horizon state: a contiguous `range` of leaves from either the UTXO or kernel MMR, along
+/// with a claim about how those leaves fold into the peer's accumulator (see [MmrRangeProof]). Chunks are streamed
+/// and validated/committed one at a time (see [SnapshotValidator]), instead of requiring the whole UTXO/kernel set
+/// to be fetched and buffered in memory before anything can be checked.
+#[derive(Debug, Clone)]
+pub struct SnapshotChunk {
+    pub version: SnapshotFormat,
+    pub tree: MmrTree,
+    pub range: Range<u64>,
+    pub leaves: Vec<Hash>,
+    pub range_proof: MmrRangeProof,
+}
+
+/// An append-only Merkle Mountain Range peak accumulator. Appending leaves merges equal-height peaks pairwise
+/// (the standard MMR append algorithm), so the number of peaks stays `O(log n)` regardless of how many leaves have
+/// been folded in, which is what makes it cheap to carry between chunks in [ChunkProgress].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PeakAccumulator {
+    /// `(height, hash)` for each current peak, left to right in the order they were created.
+    peaks: Vec<(u32, Hash)>,
+}
+
+impl PeakAccumulator {
+    fn peak_hashes(&self) -> Vec<Hash> {
+        self.peaks.iter().map(|(_, hash)| hash.clone()).collect()
+    }
+
+    fn append(&mut self, leaves: &[Hash]) {
+        for leaf in leaves {
+            let mut height = 0u32;
+            let mut hash = leaf.clone();
+            while self.peaks.last().map(|(h, _)| *h) == Some(height) {
+                let (_, sibling) = self.peaks.pop().expect("just checked peaks is non-empty");
+                hash = HashDigest::new().chain(&sibling).chain(&hash).result().to_vec();
+                height += 1;
+            }
+            self.peaks.push((height, hash));
+        }
+    }
+
+    /// Bags all current peaks right-to-left into a single root hash, the standard way to produce a single MMR root
+    /// from a set of peaks.
+    fn bagged_root(&self) -> Hash {
+        let mut iter = self.peaks.iter().rev();
+        let mut root = match iter.next() {
+            Some((_, hash)) => hash.clone(),
+            None => HashDigest::new().result().to_vec(),
+        };
+        for (_, hash) in iter {
+            root = HashDigest::new().chain(hash).chain(&root).result().to_vec();
+        }
+        root
+    }
+}
+
+#[derive(Default)]
+struct ChunkProgress {
+    utxo_next_index: u64,
+    kernel_next_index: u64,
+    utxo_accumulator: PeakAccumulator,
+    kernel_accumulator: PeakAccumulator,
+}
+
+/// Validates a stream of [SnapshotChunk]s as they arrive from a horizon sync peer. Each tree's leaves are folded,
+/// one chunk at a time, into a [PeakAccumulator] this validator owns and maintains itself: a chunk is only accepted
+/// if its claimed `peaks` match the accumulator's current (already-verified) state, and the resulting root is
+/// computed locally from that state plus the chunk's own leaves rather than trusting whatever root the chunk
+/// claims. This ties every chunk to the chunks that came before it, so a peer cannot fabricate an arbitrary tail
+/// chunk whose self-consistent hash happens to equal the horizon header's committed root. Once a tree's final chunk
+/// has been seen, the locally-computed root must equal the one the horizon header already commits to
+/// (`BlockHeader::output_mr`/`kernel_mr`). This replaces buffering the entire `fetch_all_utxos`/`fetch_all_kernels`
+/// result before any validation can run with a streaming, restart-safe protocol.
+pub struct SnapshotValidator<B> {
+    db: BlockchainDatabase<B>,
+    progress: Mutex<ChunkProgress>,
+}
+
+impl<B: BlockchainBackend> SnapshotValidator<B> {
+    pub fn new(db: BlockchainDatabase<B>) -> Self {
+        Self {
+            db,
+            progress: Mutex::new(ChunkProgress::default()),
+        }
+    }
+
+    fn expected_root(&self, tree: MmrTree, horizon_header: &BlockHeader) -> Hash {
+        match tree {
+            MmrTree::Utxo => horizon_header.output_mr.clone(),
+            MmrTree::Kernel => horizon_header.kernel_mr.clone(),
+            MmrTree::RangeProof => horizon_header.range_proof_mr.clone(),
+        }
+    }
+}
+
+impl<B: BlockchainBackend> StatelessValidation<SnapshotChunk> for SnapshotValidator<B> {
+    fn validate(&self, chunk: &SnapshotChunk) -> Result<(), ValidationError> {
+        if chunk.version != SnapshotFormat::CURRENT {
+            return Err(ValidationError::custom_error(format!(
+                "Cannot validate snapshot chunk with format version {}",
+                chunk.version.as_byte()
+            )));
+        }
+
+        if chunk.range.end.saturating_sub(chunk.range.start) != chunk.leaves.len() as u64 {
+            return Err(ValidationError::custom_error(
+                "Snapshot chunk range did not match the number of leaves supplied",
+            ));
+        }
+
+        let mut progress = self.progress.lock().unwrap();
+        let (next_index, accumulator) = match chunk.tree {
+            MmrTree::Utxo => (&mut progress.utxo_next_index, &mut progress.utxo_accumulator),
+            MmrTree::Kernel => (&mut progress.kernel_next_index, &mut progress.kernel_accumulator),
+            MmrTree::RangeProof => {
+                return Err(ValidationError::custom_error(
+                    "Horizon snapshots do not chunk the range proof MMR",
+                ))
+            },
+        };
+        if chunk.range.start != *next_index {
+            return Err(ValidationError::custom_error(format!(
+                "Snapshot chunk for {:?} did not chain on from the previous chunk: expected range to start at {}, \
+                 got {}",
+                chunk.tree, *next_index, chunk.range.start
+            )));
+        }
+
+        // The chunk's claimed `peaks` must match the state this validator has already independently verified from
+        // every prior chunk - a peer cannot just invent peaks that happen to bag to the target root.
+        if chunk.range_proof.peaks != accumulator.peak_hashes() {
+            return Err(ValidationError::custom_error(format!(
+                "Snapshot chunk for {:?} claimed peaks that do not match the previously verified accumulator state",
+                chunk.tree
+            )));
+        }
+
+        let mut updated = accumulator.clone();
+        updated.append(&chunk.leaves);
+        let computed_root = updated.bagged_root();
+        if computed_root != chunk.range_proof.root {
+            return Err(ValidationError::custom_error(format!(
+                "Snapshot chunk for {:?} root did not match the root computed by folding its leaves onto the \
+                 previously verified accumulator",
+                chunk.tree
+            )));
+        }
+
+        *accumulator = updated;
+        *next_index = chunk.range.end;
+
+        let tip_header = self
+            .db
+            .fetch_last_header()
+            .map_err(ValidationError::custom_error)?;
+        let node_count = self
+            .db
+            .fetch_mmr_node_count(chunk.tree, tip_header.height)
+            .map_err(ValidationError::custom_error)?;
+        if chunk.range.end == node_count {
+            debug!(
+                target: LOG_TARGET,
+                "Received final snapshot chunk for {:?}, accumulated root is complete", chunk.tree
+            );
+            let expected = self.expected_root(chunk.tree, &tip_header);
+            if computed_root != expected {
+                return Err(ValidationError::custom_error(format!(
+                    "Accumulated snapshot root for {:?} did not match the horizon header",
+                    chunk.tree
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaf(byte: u8) -> Hash {
+        vec![byte; 32]
+    }
+
+    #[test]
+    fn it_matches_a_naive_full_rebuild() {
+        let leaves = vec![leaf(1), leaf(2), leaf(3), leaf(4), leaf(5)];
+
+        let mut one_shot = PeakAccumulator::default();
+        one_shot.append(&leaves);
+
+        let mut incremental = PeakAccumulator::default();
+        incremental.append(&leaves[0..2]);
+        incremental.append(&leaves[2..5]);
+
+        assert_eq!(one_shot.bagged_root(), incremental.bagged_root());
+        assert_eq!(one_shot.peak_hashes(), incremental.peak_hashes());
+    }
+
+    #[test]
+    fn appending_changes_the_root() {
+        let mut acc = PeakAccumulator::default();
+        acc.append(&[leaf(1)]);
+        let root_before = acc.bagged_root();
+        acc.append(&[leaf(2)]);
+        assert_ne!(root_before, acc.bagged_root());
+    }
+
+    #[test]
+    fn empty_accumulator_has_no_peaks() {
+        let acc = PeakAccumulator::default();
+        assert!(acc.peak_hashes().is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//  Copyright 2020, The Tari Project
+//
+//  Redistribution and use in source and binary forms, with or without modification, are permitted provided that the
+//  following conditions are met:
+//
+//  1. Redistributions of source code must retain the above copyright notice, this list of conditions and the following
+//  disclaimer.
+//
+//  2. Redistributions in binary form must reproduce the above copyright notice, this list of conditions and the
+//  following disclaimer in the documentation and/or other materials provided with the distribution.
+//
+//  3. Neither the name of the copyright holder nor the names of its contributors may be used to endorse or promote
+//  products derived from this software without specific prior written permission.
+//
+//  THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES,
+//  INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+//  DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+//  SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+//  SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY,
+//  WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE
+//  USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use crate::chain_storage::Hash;
+
+/// A trust anchor committed out-of-band for a fixed block height, analogous to the epoch transition proofs warp
+/// sync uses to bound which snapshot a node will accept. Checkpoints are configured per-network on
+/// [ConsensusConstants](super::ConsensusConstants) and are only ever moved forward as a network matures.
+///
+/// This lives in the consensus layer, rather than alongside the horizon sync validators that consume it, because
+/// [ConsensusManager](super::ConsensusManager)`::consensus_constants()::nearest_checkpoint()` must be able to hand
+/// one back without the `consensus` module depending back on `base_node`.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub height: u64,
+    pub accumulated_difficulty: u128,
+    pub header_hash: Hash,
+    pub kernel_mr: Hash,
+    pub output_mr: Hash,
+    /// The trusted canonical-hash-trie root for the epoch containing `height`, used to anchor
+    /// `HorizonHeadersValidator`'s `StatelessValidation<HeaderProof>` path.
+    pub cht_root: Hash,
+}